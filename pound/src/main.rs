@@ -1,14 +1,19 @@
 use std::io::{self, stdout, Write};
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use std::{env, fs};
 
+use ropey::{Rope, RopeSlice};
 use textwrap::wrap;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::ClearType;
 use crossterm::{cursor, event, execute, queue, terminal};
 
 const VERSION: &str = "1.0.0";
+const QUIT_TIMES: u8 = 3;
+const TAB_STOP: usize = 8;
 
 struct CleanUp;
 impl Drop for CleanUp {
@@ -43,7 +48,7 @@ impl CursorController {
             x: 0,
             y: 0,
             x_max: win_size.0 - 1,
-            y_max: win_size.1 - 1,
+            y_max: win_size.1 - 1 - 2,
             row_offset: 0,
             column_offset: 0,
         };
@@ -63,21 +68,48 @@ impl CursorController {
         }
     }
 
-    fn scroll(&mut self) {
+    fn scroll(&mut self, render_x: usize) {
         self.row_offset = std::cmp::min(self.row_offset, self.y);
         if self.y >= self.row_offset + self.y_max + 1 {
             self.row_offset = self.y - self.y_max;
         }
 
-        self.column_offset = std::cmp::min(self.column_offset, self.x);
-        if self.x >= self.column_offset + self.x_max + 1 {
-            self.column_offset = self.x - self.x_max;
+        self.column_offset = std::cmp::min(self.column_offset, render_x);
+        if render_x >= self.column_offset + self.x_max + 1 {
+            self.column_offset = render_x - self.x_max;
         }
     }
 }
 
+fn expand_tabs(chars: &str) -> String {
+    let mut render = String::with_capacity(chars.len());
+    for ch in chars.chars() {
+        if ch == '\t' {
+            let spaces = TAB_STOP - (render.len() % TAB_STOP);
+            render.push_str(&" ".repeat(spaces));
+        } else {
+            render.push(ch);
+        }
+    }
+    return render;
+}
+
+// A single reversible edit: at (row, col), `removed` was replaced by `inserted`.
+// Undo re-inserts `removed` in place of `inserted`; redo replays the edit as-is.
+struct EditOp {
+    row: usize,
+    col: usize,
+    removed: String,
+    inserted: String,
+}
+
 struct EditorRows {
-    row_contents: Vec<Box<str>>,
+    rope: Rope,
+    filename: Option<PathBuf>,
+    dirty: u64,
+    undo_stack: Vec<EditOp>,
+    redo_stack: Vec<EditOp>,
+    can_coalesce: bool,
 }
 
 impl EditorRows {
@@ -86,7 +118,12 @@ impl EditorRows {
 
         match arg.nth(1) {
             None => Self {
-                row_contents: Vec::new(),
+                rope: Rope::new(),
+                filename: None,
+                dirty: 0,
+                undo_stack: Vec::new(),
+                redo_stack: Vec::new(),
+                can_coalesce: false,
             },
             Some(file) => Self::from_file(&file),
         }
@@ -96,19 +133,200 @@ impl EditorRows {
         let file_contents = fs::read_to_string(file).expect("unable to read file");
 
         return Self {
-            row_contents: file_contents.lines().map(|it| it.into()).collect(),
+            rope: Rope::from_str(&file_contents),
+            filename: Some(PathBuf::from(file)),
+            dirty: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            can_coalesce: false,
         };
     }
 
     fn number_of_rows(&self) -> usize {
-        return self.row_contents.len();
+        if self.rope.len_chars() == 0 {
+            return 0;
+        }
+
+        let len_lines = self.rope.len_lines();
+        if self.rope.line(len_lines - 1).len_chars() == 0 {
+            len_lines - 1
+        } else {
+            len_lines
+        }
+    }
+
+    fn char_idx(&self, row: usize, col: usize) -> usize {
+        if row >= self.number_of_rows() {
+            self.rope.len_chars()
+        } else {
+            let col = std::cmp::min(col, self.get_row(row).len_chars());
+            self.rope.line_to_char(row) + col
+        }
+    }
+
+    fn get_row(&self, at: usize) -> RopeSlice {
+        let line = self.rope.line(at);
+        let mut end = line.len_chars();
+        if end > 0 && line.char(end - 1) == '\n' {
+            end -= 1;
+            if end > 0 && line.char(end - 1) == '\r' {
+                end -= 1;
+            }
+        }
+        return line.slice(0..end);
+    }
+
+    fn get_render(&self, at: usize) -> String {
+        return expand_tabs(&self.get_row(at).to_string());
+    }
+
+    fn insert_char(&mut self, row: usize, col: usize, ch: char) {
+        let idx = self.char_idx(row, col);
+        self.rope.insert_char(idx, ch);
+        self.dirty += 1;
+        self.push_undo(EditOp {
+            row,
+            col,
+            removed: String::new(),
+            inserted: ch.to_string(),
+        });
+    }
+
+    fn insert_newline(&mut self, row: usize, col: usize) {
+        let idx = self.char_idx(row, col);
+        self.rope.insert_char(idx, '\n');
+        self.dirty += 1;
+        self.push_undo(EditOp {
+            row,
+            col,
+            removed: String::new(),
+            inserted: "\n".to_string(),
+        });
+    }
+
+    fn delete_char(&mut self, row: usize, col: usize) {
+        if row >= self.number_of_rows() || col >= self.get_row(row).len_chars() {
+            return;
+        }
+        let idx = self.char_idx(row, col);
+        let removed = self.rope.char(idx);
+        self.rope.remove(idx..idx + 1);
+        self.dirty += 1;
+        self.push_undo(EditOp {
+            row,
+            col,
+            removed: removed.to_string(),
+            inserted: String::new(),
+        });
+    }
+
+    fn join_row(&mut self, row: usize) {
+        let previous_row = row - 1;
+        let previous_len = self.get_row(previous_row).len_chars();
+        let idx = self.rope.line_to_char(row) - 1;
+        self.rope.remove(idx..idx + 1);
+        self.dirty += 1;
+        self.push_undo(EditOp {
+            row: previous_row,
+            col: previous_len,
+            removed: "\n".to_string(),
+            inserted: String::new(),
+        });
+    }
+
+    fn push_undo(&mut self, op: EditOp) {
+        self.redo_stack.clear();
+
+        if self.can_coalesce {
+            if let Some(top) = self.undo_stack.last_mut() {
+                let is_coalescible_insert = op.removed.is_empty()
+                    && top.removed.is_empty()
+                    && op.inserted.chars().count() == 1
+                    && op.inserted != "\n"
+                    && op.row == top.row
+                    && op.col == top.col + top.inserted.chars().count();
+
+                if is_coalescible_insert {
+                    top.inserted.push_str(&op.inserted);
+                    self.can_coalesce = true;
+                    return;
+                }
+            }
+        }
+
+        self.undo_stack.push(op);
+        self.can_coalesce = true;
     }
 
-    fn get_row(&self, at: usize) -> &str {
-        return &self.row_contents[at];
+    // Called on any cursor-only move, so that navigating away and back to the
+    // same column doesn't let an unrelated keystroke merge into the previous
+    // undo group just because the column arithmetic happens to line up.
+    fn break_coalesce(&mut self) {
+        self.can_coalesce = false;
+    }
+
+    fn undo(&mut self) -> Option<(usize, usize)> {
+        let op = self.undo_stack.pop()?;
+        let idx = self.char_idx(op.row, op.col);
+        let remove_len = op.inserted.chars().count();
+        if remove_len > 0 {
+            self.rope.remove(idx..idx + remove_len);
+        }
+        if !op.removed.is_empty() {
+            self.rope.insert(idx, &op.removed);
+        }
+        self.dirty += 1;
+        self.can_coalesce = false;
+
+        let cursor = (op.col, op.row);
+        self.redo_stack.push(op);
+        Some(cursor)
+    }
+
+    fn redo(&mut self) -> Option<(usize, usize)> {
+        let op = self.redo_stack.pop()?;
+        let idx = self.char_idx(op.row, op.col);
+        let remove_len = op.removed.chars().count();
+        if remove_len > 0 {
+            self.rope.remove(idx..idx + remove_len);
+        }
+        if !op.inserted.is_empty() {
+            self.rope.insert(idx, &op.inserted);
+        }
+        self.dirty += 1;
+        self.can_coalesce = false;
+
+        let cursor = (op.col + op.inserted.chars().count(), op.row);
+        self.undo_stack.push(op);
+        Some(cursor)
+    }
+
+    fn save_file(&mut self) -> io::Result<usize> {
+        match &self.filename {
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no file name specified",
+            )),
+            Some(name) => {
+                let contents = self.rope.to_string();
+                fs::write(name, &contents)?;
+                self.dirty = 0;
+                Ok(contents.len())
+            }
+        }
     }
 }
 
+struct SearchState {
+    query: String,
+    last_match: Option<(usize, usize)>,
+    direction: i32,
+    saved_cursor_x: usize,
+    saved_cursor_y: usize,
+    saved_row_offset: usize,
+    saved_column_offset: usize,
+}
+
 struct EditorContents {
     content: String,
 }
@@ -151,6 +369,9 @@ struct Output {
     editor_contents: EditorContents,
     cursor_controller: CursorController,
     editor_rows: EditorRows,
+    status_message: Option<(String, Instant)>,
+    search: Option<SearchState>,
+    show_gutter: bool,
 }
 impl Output {
     fn new() -> Self {
@@ -158,12 +379,33 @@ impl Output {
             .map(|(x, y)| (x as usize, y as usize))
             .unwrap();
 
-        return Self {
+        let mut output = Self {
             win_size,
             editor_contents: EditorContents::new(),
             cursor_controller: CursorController::new(win_size),
             editor_rows: EditorRows::new(),
+            status_message: None,
+            search: None,
+            show_gutter: true,
         };
+        output.sync_x_max();
+        output.set_status_message(
+            "HELP: Ctrl-S = save | Ctrl-Q = quit | Ctrl-F = find | Ctrl-L = gutter | Ctrl-Z/Ctrl-Y = undo/redo",
+        );
+        return output;
+    }
+
+    fn sync_x_max(&mut self) {
+        self.cursor_controller.x_max = self.win_size.0 - 1 - self.gutter_width();
+    }
+
+    fn toggle_gutter(&mut self) {
+        self.show_gutter = !self.show_gutter;
+        self.sync_x_max();
+    }
+
+    fn set_status_message(&mut self, message: &str) {
+        self.status_message = Some((message.into(), Instant::now()));
     }
 
     fn clear_screen() -> crossterm::Result<()> {
@@ -171,12 +413,39 @@ impl Output {
         execute!(stdout(), cursor::MoveTo(0, 0))
     }
 
+    fn gutter_width(&self) -> usize {
+        if !self.show_gutter {
+            return 0;
+        }
+
+        let rows = std::cmp::max(self.editor_rows.number_of_rows(), 1);
+        rows.ilog10() as usize + 1 + 1
+    }
+
+    fn draw_gutter(&mut self, file_row: usize, gutter_width: usize) {
+        if gutter_width == 0 {
+            return;
+        }
+
+        let number_width = gutter_width - 1;
+        if file_row < self.editor_rows.number_of_rows() {
+            let text = format!("{:>width$} ", file_row + 1, width = number_width);
+            self.editor_contents.push_str(&text);
+        } else {
+            let text = format!("{:>width$} ", "~", width = number_width);
+            self.editor_contents.push_str(&text);
+        }
+    }
+
     fn draw_rows(&mut self) {
-        let screen_colums = self.win_size.0;
-        let screen_rows = self.win_size.1;
+        let gutter_width = self.gutter_width();
+        let screen_colums = self.win_size.0.saturating_sub(gutter_width);
+        let screen_rows = self.win_size.1 - 2;
 
         for i in 0..screen_rows {
             let file_row = i + self.cursor_controller.row_offset;
+            self.draw_gutter(file_row, gutter_width);
+
             if file_row >= self.editor_rows.number_of_rows() {
                 if self.editor_rows.number_of_rows() == 0 && i == screen_rows / 3 {
                     let welcome = format!("Pound editor -- Version {}", VERSION);
@@ -188,24 +457,63 @@ impl Output {
                     } else {
                         let mut padding = (screen_colums - welcome.len()) / 2;
                         if padding != 0 {
-                            self.editor_contents.push('~');
+                            if gutter_width == 0 {
+                                self.editor_contents.push('~');
+                            }
                             padding -= 1;
                         }
                         (0..padding).for_each(|_| self.editor_contents.push(' '));
                         self.editor_contents.push_str(&welcome);
                     }
-                } else {
+                } else if gutter_width == 0 {
                     self.editor_contents.push('~');
                 }
             } else {
-                let row = self
-                    .editor_rows
-                    .get_row(i + self.cursor_controller.row_offset);
+                let row: Vec<char> = self.editor_rows.get_render(file_row).chars().collect();
                 let column_offset = self.cursor_controller.column_offset;
 
                 let len = std::cmp::min(row.len().saturating_sub(column_offset), screen_colums);
                 let start = if len == 0 { 0 } else { column_offset };
-                self.editor_contents.push_str(&row[start..start + len]);
+                let end = start + len;
+
+                let highlight = self.search.as_ref().and_then(|search| {
+                    search
+                        .last_match
+                        .filter(|(match_row, _)| *match_row == file_row)
+                        .map(|(_, col)| {
+                            let match_start = self.render_x_for_col(file_row, col);
+                            let match_end =
+                                self.render_x_for_col(file_row, col + search.query.chars().count());
+                            (match_start, match_end)
+                        })
+                });
+
+                match highlight {
+                    Some((match_start, match_end)) if match_start < end && match_end > start => {
+                        let highlight_start = std::cmp::max(match_start, start);
+                        let highlight_end = std::cmp::min(match_end, end);
+                        self.editor_contents
+                            .push_str(&row[start..highlight_start].iter().collect::<String>());
+                        queue!(
+                            self.editor_contents,
+                            SetForegroundColor(Color::Black),
+                            SetBackgroundColor(Color::Yellow)
+                        )
+                        .unwrap();
+                        self.editor_contents.push_str(
+                            &row[highlight_start..highlight_end]
+                                .iter()
+                                .collect::<String>(),
+                        );
+                        queue!(self.editor_contents, SetAttribute(Attribute::Reset)).unwrap();
+                        self.editor_contents
+                            .push_str(&row[highlight_end..end].iter().collect::<String>());
+                    }
+                    _ => {
+                        self.editor_contents
+                            .push_str(&row[start..end].iter().collect::<String>());
+                    }
+                }
             }
 
             queue!(
@@ -213,22 +521,347 @@ impl Output {
                 terminal::Clear(ClearType::UntilNewLine)
             )
             .unwrap();
-            if i < screen_rows - 1 {
-                self.editor_contents.push_str("\r\n");
+            self.editor_contents.push_str("\r\n");
+        }
+    }
+
+    fn draw_status_bar(&mut self) {
+        queue!(self.editor_contents, SetAttribute(Attribute::Reverse)).unwrap();
+
+        let filename = self
+            .editor_rows
+            .filename
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or("[No Name]");
+        let modified = if self.editor_rows.dirty > 0 {
+            " (modified)"
+        } else {
+            ""
+        };
+
+        let info = format!(
+            "{} - {} lines{}",
+            filename,
+            self.editor_rows.number_of_rows(),
+            modified
+        );
+        let info_len = std::cmp::min(info.chars().count(), self.win_size.0);
+        self.editor_contents
+            .push_str(&info.chars().take(info_len).collect::<String>());
+
+        let position = format!(
+            "{}/{}",
+            self.cursor_controller.y + 1,
+            self.editor_rows.number_of_rows()
+        );
+        for i in info_len..self.win_size.0 {
+            if self.win_size.0 - i == position.len() {
+                self.editor_contents.push_str(&position);
+                break;
+            }
+            self.editor_contents.push(' ');
+        }
+
+        queue!(self.editor_contents, SetAttribute(Attribute::Reset)).unwrap();
+        self.editor_contents.push_str("\r\n");
+    }
+
+    fn draw_message_bar(&mut self) {
+        queue!(
+            self.editor_contents,
+            terminal::Clear(ClearType::UntilNewLine)
+        )
+        .unwrap();
+
+        if let Some(search) = &self.search {
+            let message = format!("Search: {}", search.query);
+            let len = std::cmp::min(message.chars().count(), self.win_size.0);
+            self.editor_contents
+                .push_str(&message.chars().take(len).collect::<String>());
+        } else if let Some((message, time)) = &self.status_message {
+            if time.elapsed() < Duration::from_secs(5) {
+                let len = std::cmp::min(message.chars().count(), self.win_size.0);
+                self.editor_contents
+                    .push_str(&message.chars().take(len).collect::<String>());
             }
         }
     }
 
     fn move_cursor(&mut self, direction: KeyCode) {
+        self.editor_rows.break_coalesce();
         self.cursor_controller
             .move_cursor(direction, self.editor_rows.number_of_rows());
+        self.snap_x();
+    }
+
+    fn snap_x(&mut self) {
+        let line_len = if self.cursor_controller.y < self.editor_rows.number_of_rows() {
+            self.editor_rows
+                .get_row(self.cursor_controller.y)
+                .len_chars()
+        } else {
+            0
+        };
+        self.cursor_controller.x = std::cmp::min(self.cursor_controller.x, line_len);
+    }
+
+    fn move_to_line_start(&mut self) {
+        self.editor_rows.break_coalesce();
+        self.cursor_controller.x = 0;
+    }
+
+    fn move_to_line_end(&mut self) {
+        self.editor_rows.break_coalesce();
+        if self.cursor_controller.y < self.editor_rows.number_of_rows() {
+            self.cursor_controller.x = self
+                .editor_rows
+                .get_row(self.cursor_controller.y)
+                .len_chars();
+        }
+    }
+
+    fn move_word(&mut self, direction: i32) {
+        self.editor_rows.break_coalesce();
+        if self.cursor_controller.y >= self.editor_rows.number_of_rows() {
+            return;
+        }
+
+        let line: Vec<char> = self
+            .editor_rows
+            .get_row(self.cursor_controller.y)
+            .chars()
+            .collect();
+        let len = line.len();
+        let mut x = std::cmp::min(self.cursor_controller.x, len);
+
+        if direction < 0 {
+            if x == 0 {
+                return;
+            }
+            x -= 1;
+            while x > 0 && line[x].is_whitespace() {
+                x -= 1;
+            }
+            while x > 0 && !line[x - 1].is_whitespace() {
+                x -= 1;
+            }
+        } else {
+            if x >= len {
+                return;
+            }
+            while x < len && !line[x].is_whitespace() {
+                x += 1;
+            }
+            while x < len && line[x].is_whitespace() {
+                x += 1;
+            }
+        }
+
+        self.cursor_controller.x = x;
+    }
+
+    fn move_page(&mut self, direction: i32) {
+        self.editor_rows.break_coalesce();
+        let y_max = self.cursor_controller.y_max;
+        let number_of_rows = self.editor_rows.number_of_rows();
+
+        if direction < 0 {
+            self.cursor_controller.y = self.cursor_controller.y.saturating_sub(y_max);
+        } else {
+            self.cursor_controller.y =
+                std::cmp::min(self.cursor_controller.y + y_max, number_of_rows);
+        }
+
+        self.snap_x();
+    }
+
+    fn render_x_for_col(&self, row: usize, col: usize) -> usize {
+        let mut render_x = 0;
+
+        if row < self.editor_rows.number_of_rows() {
+            let line = self.editor_rows.get_row(row);
+            for ch in line.chars().take(col) {
+                if ch == '\t' {
+                    render_x += TAB_STOP - (render_x % TAB_STOP);
+                } else {
+                    render_x += 1;
+                }
+            }
+        }
+
+        return render_x;
+    }
+
+    fn cursor_render_x(&self) -> usize {
+        self.render_x_for_col(self.cursor_controller.y, self.cursor_controller.x)
+    }
+
+    fn start_find(&mut self) {
+        self.search = Some(SearchState {
+            query: String::new(),
+            last_match: None,
+            direction: 1,
+            saved_cursor_x: self.cursor_controller.x,
+            saved_cursor_y: self.cursor_controller.y,
+            saved_row_offset: self.cursor_controller.row_offset,
+            saved_column_offset: self.cursor_controller.column_offset,
+        });
+        self.set_status_message("Search (Esc to cancel, arrows to navigate): ");
+    }
+
+    fn cancel_find(&mut self) {
+        self.editor_rows.break_coalesce();
+        if let Some(search) = self.search.take() {
+            self.cursor_controller.x = search.saved_cursor_x;
+            self.cursor_controller.y = search.saved_cursor_y;
+            self.cursor_controller.row_offset = search.saved_row_offset;
+            self.cursor_controller.column_offset = search.saved_column_offset;
+        }
+        self.set_status_message("");
+    }
+
+    fn confirm_find(&mut self) {
+        self.search = None;
+        self.set_status_message("");
+    }
+
+    fn find_push_char(&mut self, ch: char) {
+        let direction = self.search.as_ref().map_or(1, |search| search.direction);
+        if let Some(search) = &mut self.search {
+            search.query.push(ch);
+            search.last_match = None;
+        }
+        self.find_next(direction);
+    }
+
+    fn find_pop_char(&mut self) {
+        let direction = self.search.as_ref().map_or(1, |search| search.direction);
+        if let Some(search) = &mut self.search {
+            search.query.pop();
+            search.last_match = None;
+        }
+        self.find_next(direction);
+    }
+
+    fn find_next(&mut self, direction: i32) {
+        self.editor_rows.break_coalesce();
+        let total_rows = self.editor_rows.number_of_rows();
+        let query = match &self.search {
+            Some(search) if !search.query.is_empty() => search.query.clone(),
+            _ => return,
+        };
+        if total_rows == 0 {
+            return;
+        }
+
+        let mut row = match self.search.as_ref().unwrap().last_match {
+            Some((row, _)) => row as i64,
+            None => -1,
+        };
+
+        for _ in 0..total_rows {
+            row += direction as i64;
+            if row < 0 {
+                row = total_rows as i64 - 1;
+            } else if row >= total_rows as i64 {
+                row = 0;
+            }
+
+            let line = self.editor_rows.get_row(row as usize).to_string();
+            if let Some(byte_col) = line.find(&query) {
+                let col = line[..byte_col].chars().count();
+                let search = self.search.as_mut().unwrap();
+                search.last_match = Some((row as usize, col));
+                search.direction = direction;
+                self.cursor_controller.y = row as usize;
+                self.cursor_controller.x = col;
+                break;
+            }
+        }
+    }
+
+    fn insert_char(&mut self, ch: char) {
+        self.editor_rows
+            .insert_char(self.cursor_controller.y, self.cursor_controller.x, ch);
+        self.cursor_controller.x += 1;
+    }
+
+    fn insert_newline(&mut self) {
+        self.editor_rows
+            .insert_newline(self.cursor_controller.y, self.cursor_controller.x);
+        self.cursor_controller.y += 1;
+        self.cursor_controller.x = 0;
+    }
+
+    fn delete_char(&mut self) {
+        if self.cursor_controller.y == self.editor_rows.number_of_rows() {
+            return;
+        }
+        if self.cursor_controller.x == 0 && self.cursor_controller.y == 0 {
+            return;
+        }
+
+        if self.cursor_controller.x > 0 {
+            self.editor_rows
+                .delete_char(self.cursor_controller.y, self.cursor_controller.x - 1);
+            self.cursor_controller.x -= 1;
+        } else {
+            let previous_row_len = self
+                .editor_rows
+                .get_row(self.cursor_controller.y - 1)
+                .len_chars();
+            self.editor_rows.join_row(self.cursor_controller.y);
+            self.cursor_controller.y -= 1;
+            self.cursor_controller.x = previous_row_len;
+        }
+    }
+
+    fn delete_char_forward(&mut self) {
+        if self.cursor_controller.y >= self.editor_rows.number_of_rows() {
+            return;
+        }
+
+        let line_len = self
+            .editor_rows
+            .get_row(self.cursor_controller.y)
+            .len_chars();
+        if self.cursor_controller.x < line_len {
+            self.editor_rows
+                .delete_char(self.cursor_controller.y, self.cursor_controller.x);
+        } else if self.cursor_controller.y + 1 < self.editor_rows.number_of_rows() {
+            self.editor_rows.join_row(self.cursor_controller.y + 1);
+        }
+    }
+
+    fn save(&mut self) -> io::Result<usize> {
+        self.editor_rows.save_file()
+    }
+
+    fn undo(&mut self) {
+        if let Some((x, y)) = self.editor_rows.undo() {
+            self.cursor_controller.x = x;
+            self.cursor_controller.y = y;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some((x, y)) = self.editor_rows.redo() {
+            self.cursor_controller.x = x;
+            self.cursor_controller.y = y;
+        }
     }
 
     fn refresh_screen(&mut self) -> crossterm::Result<()> {
-        self.cursor_controller.scroll();
+        self.sync_x_max();
+        let render_x = self.cursor_render_x();
+        self.cursor_controller.scroll(render_x);
         queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
         self.draw_rows();
-        let cursor_x = self.cursor_controller.x - self.cursor_controller.column_offset;
+        self.draw_status_bar();
+        self.draw_message_bar();
+        let cursor_x = self.gutter_width() + render_x - self.cursor_controller.column_offset;
         let cursor_y = self.cursor_controller.y - self.cursor_controller.row_offset;
         queue!(
             self.editor_contents,
@@ -240,8 +873,8 @@ impl Output {
 
     fn process_resize(&mut self, x: usize, y: usize) {
         self.win_size = (x, y);
-        self.cursor_controller.x_max = x - 1;
-        self.cursor_controller.y_max = y - 1;
+        self.sync_x_max();
+        self.cursor_controller.y_max = y - 1 - 2;
         self.cursor_controller.x =
             std::cmp::min(self.cursor_controller.x, self.cursor_controller.x_max);
         self.cursor_controller.y =
@@ -252,6 +885,7 @@ impl Output {
 struct Editor {
     reader: Reader,
     output: Output,
+    quit_times: u8,
 }
 
 impl Editor {
@@ -259,26 +893,187 @@ impl Editor {
         return Self {
             reader: Reader,
             output: Output::new(),
+            quit_times: QUIT_TIMES,
         };
     }
 
+    fn process_find_keypress(&mut self, key_event: KeyEvent) -> crossterm::Result<bool> {
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } => {
+                self.output.cancel_find();
+            }
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            } => {
+                self.output.confirm_find();
+            }
+            KeyEvent {
+                code: KeyCode::Up | KeyCode::Left,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.find_next(-1);
+            }
+            KeyEvent {
+                code: KeyCode::Down | KeyCode::Right,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.find_next(1);
+            }
+            KeyEvent {
+                code: KeyCode::Backspace,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.find_pop_char();
+            }
+            KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            } => {
+                self.output.find_push_char(ch);
+            }
+            _ => {}
+        }
+
+        return Ok(true);
+    }
+
     fn process_keypress(&mut self, key_event: KeyEvent) -> crossterm::Result<bool> {
+        if self.output.search.is_some() {
+            return self.process_find_keypress(key_event);
+        }
+
         match key_event {
             KeyEvent {
                 code: KeyCode::Char('q'),
                 modifiers: event::KeyModifiers::CONTROL,
             } => {
+                if self.output.editor_rows.dirty > 0 && self.quit_times > 0 {
+                    self.output.set_status_message(&format!(
+                        "File has unsaved changes. Press Ctrl-Q {} more times to quit.",
+                        self.quit_times
+                    ));
+                    self.quit_times -= 1;
+                    return Ok(true);
+                }
+
                 return Ok(false);
             }
+            KeyEvent {
+                code: KeyCode::Char('f'),
+                modifiers: event::KeyModifiers::CONTROL,
+            } => {
+                self.output.start_find();
+            }
+            KeyEvent {
+                code: KeyCode::Char('l'),
+                modifiers: event::KeyModifiers::CONTROL,
+            } => {
+                self.output.toggle_gutter();
+            }
+            KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: event::KeyModifiers::CONTROL,
+            } => {
+                self.output.undo();
+            }
+            KeyEvent {
+                code: KeyCode::Char('y'),
+                modifiers: event::KeyModifiers::CONTROL,
+            } => {
+                self.output.redo();
+            }
+            KeyEvent {
+                code: KeyCode::Home,
+                modifiers: KeyModifiers::NONE,
+            }
+            | KeyEvent {
+                code: KeyCode::Char('a'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.output.move_to_line_start();
+            }
+            KeyEvent {
+                code: KeyCode::End,
+                modifiers: KeyModifiers::NONE,
+            }
+            | KeyEvent {
+                code: KeyCode::Char('e'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.output.move_to_line_end();
+            }
+            KeyEvent {
+                code: KeyCode::Left,
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.output.move_word(-1);
+            }
+            KeyEvent {
+                code: KeyCode::Right,
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.output.move_word(1);
+            }
+            KeyEvent {
+                code: KeyCode::PageUp,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.move_page(-1);
+            }
+            KeyEvent {
+                code: KeyCode::PageDown,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.move_page(1);
+            }
+            KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: event::KeyModifiers::CONTROL,
+            } => match self.output.save() {
+                Ok(bytes) => self
+                    .output
+                    .set_status_message(&format!("{} bytes written to disk", bytes)),
+                Err(err) => self
+                    .output
+                    .set_status_message(&format!("Can't save! I/O error: {}", err)),
+            },
             KeyEvent {
                 code: direction @ (KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right),
                 modifiers: KeyModifiers::NONE,
             } => {
                 self.output.move_cursor(direction);
             }
+            KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.insert_newline();
+            }
+            KeyEvent {
+                code: KeyCode::Backspace,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.delete_char();
+            }
+            KeyEvent {
+                code: KeyCode::Delete,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.output.delete_char_forward();
+            }
+            KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            } => {
+                self.output.insert_char(ch);
+            }
             _ => {}
         }
 
+        self.quit_times = QUIT_TIMES;
         return Ok(true);
     }
 